@@ -0,0 +1,220 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Read-through LRU cache in front of an `AdminServiceEventStore`.
+//!
+//! `list_events` runs several queries and rebuilds every `CircuitProposal`/`ProposedCircuit` from
+//! scratch on each call, even for events that are immutable once written.
+//! `CachedAdminServiceEventStore` wraps any `AdminServiceEventStore` with an
+//! `LruCache<i64, AdminServiceEvent>`: on `list_events` it partitions the requested IDs into
+//! cache hits and misses, queries the inner store only for the misses, and inserts the freshly
+//! built events into the cache before merging. Because admin events are append-only and never
+//! mutated after creation, the cache needs no invalidation beyond ordinary LRU eviction.
+
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+use lru::LruCache;
+
+use super::{AdminServiceEventStore, AdminServiceEventStoreError, EventIter};
+use crate::admin::service::event::AdminServiceEvent;
+
+/// Capacity used by `CachedAdminServiceEventStore::new`; callers with different memory budgets
+/// should use `with_capacity` instead.
+const DEFAULT_CACHE_CAPACITY: usize = 512;
+
+/// Decorates an `AdminServiceEventStore` with a bounded, read-through LRU cache keyed by event
+/// ID.
+pub struct CachedAdminServiceEventStore {
+    inner: Box<dyn AdminServiceEventStore>,
+    cache: Mutex<LruCache<i64, AdminServiceEvent>>,
+}
+
+impl CachedAdminServiceEventStore {
+    /// Wraps `inner` with a cache of `DEFAULT_CACHE_CAPACITY` entries.
+    pub fn new(inner: Box<dyn AdminServiceEventStore>) -> Self {
+        CachedAdminServiceEventStore::with_capacity(inner, DEFAULT_CACHE_CAPACITY)
+    }
+
+    /// Wraps `inner` with a cache bounded to `capacity` entries; a `capacity` of 0 falls back to
+    /// `DEFAULT_CACHE_CAPACITY`.
+    pub fn with_capacity(inner: Box<dyn AdminServiceEventStore>, capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity)
+            .unwrap_or_else(|| NonZeroUsize::new(DEFAULT_CACHE_CAPACITY).expect("default cache capacity is nonzero"));
+        CachedAdminServiceEventStore {
+            inner,
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+}
+
+impl AdminServiceEventStore for CachedAdminServiceEventStore {
+    fn list_events(&self, event_ids: Vec<i64>) -> Result<EventIter, AdminServiceEventStoreError> {
+        // The inner store collapses duplicate IDs via `eq_any` + a `HashMap`; dedupe here too so
+        // a request with repeated IDs doesn't produce repeated rows in the output.
+        let mut seen = HashSet::with_capacity(event_ids.len());
+        let mut hits = Vec::with_capacity(event_ids.len());
+        let mut misses = Vec::new();
+        {
+            let mut cache = self.cache.lock().expect("event cache lock poisoned");
+            for event_id in &event_ids {
+                if !seen.insert(*event_id) {
+                    continue;
+                }
+                match cache.get(event_id) {
+                    Some(event) => hits.push(event.clone()),
+                    None => misses.push(*event_id),
+                }
+            }
+        }
+
+        if !misses.is_empty() {
+            let mut cache = self.cache.lock().expect("event cache lock poisoned");
+            for event in self.inner.list_events(misses)? {
+                cache.put(event.event_id, event.clone());
+                hits.push(event);
+            }
+        }
+
+        // `hits` interleaves cache hits (in request order) with freshly-fetched misses; restore
+        // the ascending-by-event-ID order that every other `list_events` implementation returns.
+        hits.sort_by_key(|event| event.event_id);
+
+        Ok(Box::new(hits.into_iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    use crate::admin::service::event::store::diesel::models::AdminServiceEventModel;
+    use crate::admin::store::{
+        AuthorizationType, CircuitProposalBuilder, DurabilityType, PersistenceType, ProposalType,
+        ProposedCircuitBuilder, RouteType,
+    };
+
+    fn sample_event(event_id: i64) -> AdminServiceEvent {
+        let circuit = ProposedCircuitBuilder::new()
+            .with_circuit_id("circuit-1")
+            .with_authorization_type(&AuthorizationType::Trust)
+            .with_persistence(&PersistenceType::Accept)
+            .with_durability(&DurabilityType::NoDurability)
+            .with_routes(&RouteType::Any)
+            .with_circuit_management_type("test-app")
+            .build()
+            .expect("failed to build circuit");
+
+        let proposal = CircuitProposalBuilder::new()
+            .with_proposal_type(&ProposalType::Create)
+            .with_circuit_id("circuit-1")
+            .with_circuit_hash("circuit-hash")
+            .with_requester(b"requester")
+            .with_requester_node_id("node-1")
+            .with_circuit(&circuit)
+            .build()
+            .expect("failed to build proposal");
+
+        let model = AdminServiceEventModel {
+            id: event_id,
+            event_type: "ProposalSubmitted".into(),
+            circuit_snapshot_hash: "snapshot-hash".into(),
+            timestamp: 0,
+        };
+
+        AdminServiceEvent::try_from((model, proposal)).expect("failed to build event")
+    }
+
+    /// An inner store that serves `list_events` out of a fixed in-memory set and records every
+    /// batch of IDs it was asked for, so tests can assert the cache actually avoided querying it.
+    struct MockStore {
+        events: std::collections::HashMap<i64, AdminServiceEvent>,
+        queries: std::sync::Arc<Mutex<Vec<Vec<i64>>>>,
+    }
+
+    impl AdminServiceEventStore for MockStore {
+        fn list_events(&self, event_ids: Vec<i64>) -> Result<EventIter, AdminServiceEventStoreError> {
+            self.queries.lock().expect("query log lock poisoned").push(event_ids.clone());
+            let events: Vec<AdminServiceEvent> = event_ids
+                .into_iter()
+                .filter_map(|id| self.events.get(&id).cloned())
+                .collect();
+            Ok(Box::new(events.into_iter()))
+        }
+    }
+
+    fn mock_store(event_ids: &[i64]) -> MockStore {
+        MockStore {
+            events: event_ids.iter().map(|id| (*id, sample_event(*id))).collect(),
+            queries: std::sync::Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Duplicate IDs in a single request must not produce duplicate rows in the output, matching
+    /// the inner store's own `eq_any` + `HashMap` dedup semantics.
+    #[test]
+    fn dedupes_repeated_ids_in_one_request() {
+        let store = CachedAdminServiceEventStore::new(Box::new(mock_store(&[1, 2])));
+
+        let events: Vec<AdminServiceEvent> = store
+            .list_events(vec![1, 2, 1, 2])
+            .expect("list_events failed")
+            .collect();
+
+        assert_eq!(
+            events.iter().map(|event| event.event_id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+
+    /// A request mixing already-cached and not-yet-cached IDs returns both, in ascending order.
+    #[test]
+    fn merges_hits_and_misses_in_order() {
+        let inner = mock_store(&[1, 2, 3]);
+        let store = CachedAdminServiceEventStore::new(Box::new(inner));
+
+        // Warm the cache with event 2 only.
+        let _: Vec<AdminServiceEvent> = store.list_events(vec![2]).unwrap().collect();
+
+        let events: Vec<AdminServiceEvent> = store.list_events(vec![3, 2, 1]).unwrap().collect();
+
+        assert_eq!(
+            events.iter().map(|event| event.event_id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    /// Once an ID has been served, a later request for it is satisfied entirely from the cache --
+    /// the inner store is never asked for it again.
+    #[test]
+    fn repeated_requests_do_not_hit_the_inner_store_again() {
+        let inner = mock_store(&[1]);
+        let queries = inner.queries.clone();
+        let store = CachedAdminServiceEventStore::with_capacity(Box::new(inner), 8);
+
+        let _: Vec<AdminServiceEvent> = store.list_events(vec![1]).unwrap().collect();
+        let _: Vec<AdminServiceEvent> = store.list_events(vec![1]).unwrap().collect();
+
+        let events: Vec<AdminServiceEvent> = store.list_events(vec![1]).unwrap().collect();
+        assert_eq!(events.len(), 1);
+        // Only the first request reached the inner store; the later two were served from cache.
+        assert_eq!(
+            *queries.lock().expect("query log lock poisoned"),
+            vec![vec![1]]
+        );
+    }
+}
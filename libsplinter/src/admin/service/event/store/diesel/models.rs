@@ -0,0 +1,130 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Diesel `Queryable`/`Insertable` models backing the admin service event store.
+
+use super::schema::{
+    admin_event_circuit_proposal, admin_event_circuit_snapshot,
+    admin_event_circuit_snapshot_node, admin_event_circuit_snapshot_node_endpoint,
+    admin_event_circuit_snapshot_service, admin_event_circuit_snapshot_service_argument,
+    admin_event_vote_record, admin_service_event,
+};
+
+#[derive(Debug, PartialEq, Queryable, Identifiable)]
+#[table_name = "admin_service_event"]
+pub struct AdminServiceEventModel {
+    pub id: i64,
+    pub event_type: String,
+    pub circuit_snapshot_hash: String,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, PartialEq, Queryable, Insertable)]
+#[table_name = "admin_service_event"]
+pub struct NewAdminServiceEventModel<'a> {
+    pub event_type: &'a str,
+    pub circuit_snapshot_hash: &'a str,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, PartialEq, Queryable, Identifiable)]
+#[table_name = "admin_event_circuit_proposal"]
+#[primary_key(event_id)]
+pub struct AdminEventCircuitProposalModel {
+    pub event_id: i64,
+    pub proposal_type: String,
+    pub circuit_id: String,
+    pub circuit_hash: String,
+    pub previous_circuit_hash: Option<String>,
+    pub requester: Vec<u8>,
+    pub requester_node_id: String,
+}
+
+#[derive(Debug, PartialEq, Queryable, Insertable)]
+#[table_name = "admin_event_circuit_proposal"]
+pub struct NewAdminEventCircuitProposalModel<'a> {
+    pub event_id: i64,
+    pub proposal_type: &'a str,
+    pub circuit_id: &'a str,
+    pub circuit_hash: &'a str,
+    pub previous_circuit_hash: Option<&'a str>,
+    pub requester: &'a [u8],
+    pub requester_node_id: &'a str,
+}
+
+#[derive(Debug, PartialEq, Queryable, Identifiable)]
+#[table_name = "admin_event_vote_record"]
+pub struct AdminEventVoteRecordModel {
+    pub id: i64,
+    pub event_id: i64,
+    pub public_key: Vec<u8>,
+    pub vote: String,
+    pub voter_node_id: String,
+}
+
+/// A deduplicated `ProposedCircuit` snapshot, keyed by the content hash computed in
+/// `snapshot_hash::hash_proposed_circuit`. Every `AdminServiceEvent` whose proposed circuit
+/// hashes identically shares this single row.
+#[derive(Debug, PartialEq, Queryable, Identifiable, Insertable)]
+#[table_name = "admin_event_circuit_snapshot"]
+#[primary_key(circuit_snapshot_hash)]
+pub struct AdminEventCircuitSnapshotModel {
+    pub circuit_snapshot_hash: String,
+    pub circuit_id: String,
+    pub authorization_type: String,
+    pub persistence: String,
+    pub durability: String,
+    pub routes: String,
+    pub circuit_management_type: String,
+    pub application_metadata: Option<Vec<u8>>,
+    pub comments: Option<String>,
+    pub display_name: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Queryable, Identifiable, Insertable)]
+#[table_name = "admin_event_circuit_snapshot_service"]
+#[primary_key(circuit_snapshot_hash, service_id)]
+pub struct AdminEventCircuitSnapshotServiceModel {
+    pub circuit_snapshot_hash: String,
+    pub service_id: String,
+    pub service_type: String,
+    pub node_id: String,
+}
+
+#[derive(Debug, PartialEq, Queryable, Identifiable, Insertable)]
+#[table_name = "admin_event_circuit_snapshot_service_argument"]
+#[primary_key(circuit_snapshot_hash, service_id, key)]
+pub struct AdminEventCircuitSnapshotServiceArgumentModel {
+    pub circuit_snapshot_hash: String,
+    pub service_id: String,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, PartialEq, Queryable, Identifiable, Insertable)]
+#[table_name = "admin_event_circuit_snapshot_node"]
+#[primary_key(circuit_snapshot_hash, node_id)]
+pub struct AdminEventCircuitSnapshotNodeModel {
+    pub circuit_snapshot_hash: String,
+    pub node_id: String,
+}
+
+#[derive(Debug, PartialEq, Queryable, Identifiable, Insertable)]
+#[table_name = "admin_event_circuit_snapshot_node_endpoint"]
+#[primary_key(circuit_snapshot_hash, node_id, endpoint)]
+pub struct AdminEventCircuitSnapshotNodeEndpointModel {
+    pub circuit_snapshot_hash: String,
+    pub node_id: String,
+    pub endpoint: String,
+}
@@ -0,0 +1,218 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Used by operations to commit a new `AdminServiceEvent`, deduplicating its `ProposedCircuit`
+//! against previously-committed snapshots and notifying live subscribers once it is durable.
+
+use std::convert::TryFrom;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use diesel::{prelude::*, types::HasSqlType};
+
+use super::AdminServiceEventStoreOperations;
+
+use crate::admin::service::event::{
+    store::{
+        diesel::{
+            models::{
+                AdminEventCircuitSnapshotModel, AdminEventCircuitSnapshotNodeModel,
+                AdminEventCircuitSnapshotNodeEndpointModel,
+                AdminEventCircuitSnapshotServiceArgumentModel,
+                AdminEventCircuitSnapshotServiceModel, AdminServiceEventModel,
+                NewAdminEventCircuitProposalModel, NewAdminServiceEventModel,
+            },
+            schema::{
+                admin_event_circuit_proposal, admin_event_circuit_snapshot,
+                admin_event_circuit_snapshot_node, admin_event_circuit_snapshot_node_endpoint,
+                admin_event_circuit_snapshot_service, admin_event_circuit_snapshot_service_argument,
+                admin_service_event,
+            },
+            snapshot_hash::hash_proposed_circuit,
+        },
+        AdminServiceEventStoreError,
+    },
+    AdminServiceEvent,
+};
+use crate::admin::store::CircuitProposal;
+
+// Connection-scoped "last ID this statement inserted" primitives. Unlike `order(id.desc()).first()`,
+// these can only ever return a row this same connection just wrote, so they stay correct under
+// concurrent writers at any isolation level.
+#[cfg(feature = "postgres")]
+diesel::sql_function!(fn lastval() -> diesel::sql_types::BigInt);
+
+#[cfg(feature = "sqlite")]
+diesel::sql_function!(fn last_insert_rowid() -> diesel::sql_types::BigInt);
+
+pub(in crate::admin::service::event::store::diesel) trait AdminServiceEventStoreAddEventOperation
+{
+    /// Commits a new `AdminServiceEvent` of `event_type` for `proposal`. The proposal's
+    /// `ProposedCircuit` is hashed and, only on a miss, written once to
+    /// `admin_event_circuit_snapshot`; the event row itself stores just the hash. Once the
+    /// transaction committing it has returned, the event is also passed to
+    /// `AdminServiceEventSubscriberFactory::notify` so any live `subscribe` stream tailing the
+    /// store picks it up.
+    fn add_event(
+        &self,
+        event_type: &str,
+        proposal: &CircuitProposal,
+    ) -> Result<AdminServiceEvent, AdminServiceEventStoreError>;
+}
+
+impl<'a, C> AdminServiceEventStoreAddEventOperation for AdminServiceEventStoreOperations<'a, C>
+where
+    C: diesel::Connection,
+    C::Backend: HasSqlType<diesel::sql_types::BigInt>,
+    String: diesel::deserialize::FromSql<diesel::sql_types::Text, C::Backend>,
+    i64: diesel::deserialize::FromSql<diesel::sql_types::BigInt, C::Backend>,
+    Vec<u8>: diesel::deserialize::FromSql<diesel::sql_types::Binary, C::Backend>,
+{
+    fn add_event(
+        &self,
+        event_type: &str,
+        proposal: &CircuitProposal,
+    ) -> Result<AdminServiceEvent, AdminServiceEventStoreError> {
+        self.conn.transaction::<AdminServiceEvent, _, _>(|| {
+            let circuit = proposal.circuit();
+            let snapshot_hash = hash_proposed_circuit(circuit);
+
+            // Write the snapshot only on a miss; every subsequent event for an unchanged
+            // `ProposedCircuit` reuses this row instead of duplicating it.
+            let snapshot_exists = diesel::dsl::select(diesel::dsl::exists(
+                admin_event_circuit_snapshot::table.filter(
+                    admin_event_circuit_snapshot::circuit_snapshot_hash.eq(snapshot_hash.as_str()),
+                ),
+            ))
+            .get_result::<bool>(self.conn)?;
+
+            if !snapshot_exists {
+                diesel::insert_into(admin_event_circuit_snapshot::table)
+                    .values(AdminEventCircuitSnapshotModel {
+                        circuit_snapshot_hash: snapshot_hash.as_str().to_string(),
+                        circuit_id: circuit.circuit_id().to_string(),
+                        authorization_type: circuit.authorization_type().to_string(),
+                        persistence: circuit.persistence().to_string(),
+                        durability: circuit.durability().to_string(),
+                        routes: circuit.routes().to_string(),
+                        circuit_management_type: circuit.circuit_management_type().to_string(),
+                        application_metadata: circuit.application_metadata().map(|m| m.to_vec()),
+                        comments: circuit.comments().map(|c| c.to_string()),
+                        display_name: circuit.display_name().map(|d| d.to_string()),
+                    })
+                    .execute(self.conn)?;
+
+                for service in circuit.roster() {
+                    diesel::insert_into(admin_event_circuit_snapshot_service::table)
+                        .values(AdminEventCircuitSnapshotServiceModel {
+                            circuit_snapshot_hash: snapshot_hash.as_str().to_string(),
+                            service_id: service.service_id().to_string(),
+                            service_type: service.service_type().to_string(),
+                            node_id: service.node_id().to_string(),
+                        })
+                        .execute(self.conn)?;
+
+                    for (key, value) in service.arguments() {
+                        diesel::insert_into(admin_event_circuit_snapshot_service_argument::table)
+                            .values(AdminEventCircuitSnapshotServiceArgumentModel {
+                                circuit_snapshot_hash: snapshot_hash.as_str().to_string(),
+                                service_id: service.service_id().to_string(),
+                                key: key.to_string(),
+                                value: value.to_string(),
+                            })
+                            .execute(self.conn)?;
+                    }
+                }
+
+                for node in circuit.members() {
+                    diesel::insert_into(admin_event_circuit_snapshot_node::table)
+                        .values(AdminEventCircuitSnapshotNodeModel {
+                            circuit_snapshot_hash: snapshot_hash.as_str().to_string(),
+                            node_id: node.node_id().to_string(),
+                        })
+                        .execute(self.conn)?;
+
+                    for endpoint in node.endpoints() {
+                        diesel::insert_into(admin_event_circuit_snapshot_node_endpoint::table)
+                            .values(AdminEventCircuitSnapshotNodeEndpointModel {
+                                circuit_snapshot_hash: snapshot_hash.as_str().to_string(),
+                                node_id: node.node_id().to_string(),
+                                endpoint: endpoint.to_string(),
+                            })
+                            .execute(self.conn)?;
+                    }
+                }
+            }
+
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64;
+
+            diesel::insert_into(admin_service_event::table)
+                .values(NewAdminServiceEventModel {
+                    event_type,
+                    circuit_snapshot_hash: snapshot_hash.as_str(),
+                    timestamp,
+                })
+                .execute(self.conn)?;
+
+            // `order(id.desc()).first()` would be a TOCTOU race here: under concurrent writers,
+            // another transaction's commit could land between this INSERT and that SELECT, and
+            // this transaction would read back someone else's event instead of its own.
+            // `lastval()`/`last_insert_rowid()` are scoped to this connection, so they can only
+            // ever name the row this transaction itself just inserted.
+            #[cfg(feature = "postgres")]
+            let inserted_id: i64 = diesel::select(lastval()).get_result(self.conn)?;
+            #[cfg(feature = "sqlite")]
+            let inserted_id: i64 = diesel::select(last_insert_rowid()).get_result(self.conn)?;
+
+            let event_model = admin_service_event::table
+                .filter(admin_service_event::id.eq(inserted_id))
+                .first::<AdminServiceEventModel>(self.conn)?;
+
+            // The proposal this one extends is whichever proposal for the same `circuit_id` was
+            // committed most recently; `None` means `proposal` is the circuit's first. This edge
+            // is recorded here, at write time, specifically so later readers (e.g.
+            // `list_sorted_events`) can walk the dependency DAG without ever consulting
+            // `event_id`.
+            let previous_circuit_hash: Option<String> = admin_event_circuit_proposal::table
+                .filter(admin_event_circuit_proposal::circuit_id.eq(proposal.circuit_id()))
+                .order(admin_event_circuit_proposal::event_id.desc())
+                .select(admin_event_circuit_proposal::circuit_hash)
+                .first::<String>(self.conn)
+                .optional()?;
+
+            diesel::insert_into(admin_event_circuit_proposal::table)
+                .values(NewAdminEventCircuitProposalModel {
+                    event_id: event_model.id,
+                    proposal_type: &proposal.proposal_type().to_string(),
+                    circuit_id: proposal.circuit_id(),
+                    circuit_hash: proposal.circuit_hash(),
+                    previous_circuit_hash: previous_circuit_hash.as_deref(),
+                    requester: proposal.requester(),
+                    requester_node_id: proposal.requester_node_id(),
+                })
+                .execute(self.conn)?;
+
+            AdminServiceEvent::try_from((event_model, proposal.clone()))
+        })
+        .map(|event| {
+            // `transaction` only returns once the above has committed, so subscribers are
+            // notified of an event that is actually durable, never one that might still roll
+            // back.
+            self.subscribers.notify(&event);
+            event
+        })
+    }
+}
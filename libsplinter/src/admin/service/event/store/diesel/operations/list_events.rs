@@ -26,14 +26,15 @@ use crate::admin::service::event::{
     store::{
         diesel::{
             models::{
-                AdminEventCircuitProposalModel, AdminEventProposedCircuitModel,
-                AdminEventProposedNodeModel, AdminEventProposedServiceArgumentModel,
-                AdminEventProposedServiceModel, AdminEventVoteRecordModel, AdminServiceEventModel,
+                AdminEventCircuitProposalModel, AdminEventCircuitSnapshotModel,
+                AdminEventCircuitSnapshotNodeModel, AdminEventCircuitSnapshotServiceArgumentModel,
+                AdminEventCircuitSnapshotServiceModel, AdminEventVoteRecordModel,
+                AdminServiceEventModel,
             },
             schema::{
-                admin_event_circuit_proposal, admin_event_proposed_circuit,
-                admin_event_proposed_node, admin_event_proposed_node_endpoint,
-                admin_event_proposed_service, admin_event_proposed_service_argument,
+                admin_event_circuit_proposal, admin_event_circuit_snapshot,
+                admin_event_circuit_snapshot_node, admin_event_circuit_snapshot_node_endpoint,
+                admin_event_circuit_snapshot_service, admin_event_circuit_snapshot_service_argument,
                 admin_event_vote_record, admin_service_event,
             },
         },
@@ -46,6 +47,7 @@ use crate::admin::store::{
     ProposedCircuitBuilder, ProposedNode, ProposedNodeBuilder, ProposedService,
     ProposedServiceBuilder, RouteType, VoteRecord,
 };
+use crate::error::InvalidStateError;
 
 pub(in crate::admin::service::event::store::diesel) trait AdminServiceEventStoreListEventsOperation
 {
@@ -62,38 +64,28 @@ where
 {
     fn list_events(&self, event_ids: Vec<i64>) -> Result<EventIter, AdminServiceEventStoreError> {
         self.conn.transaction::<EventIter, _, _>(|| {
-            // List of the events, and the one-to-one models present in the database
-            let event_models: Vec<(
-                AdminServiceEventModel,
-                AdminEventCircuitProposalModel,
-                AdminEventProposedCircuitModel,
-            )> = admin_service_event::table
-                .filter(admin_service_event::id.eq_any(&event_ids))
-                .inner_join(
-                    admin_event_circuit_proposal::table
-                        .on(admin_service_event::id.eq(admin_event_circuit_proposal::event_id)),
-                )
-                .inner_join(
-                    admin_event_proposed_circuit::table
-                        .on(admin_service_event::id.eq(admin_event_proposed_circuit::event_id)),
-                )
-                .load::<(
-                    AdminServiceEventModel,
-                    AdminEventCircuitProposalModel,
-                    AdminEventProposedCircuitModel,
-                )>(self.conn)?;
+            // List of the events and their one-to-one `CircuitProposal` models. The
+            // `ProposedCircuit` is intentionally not joined here: `circuit_snapshot_hash`
+            // identifies which row in `admin_event_circuit_snapshot` holds it, and that row is
+            // shared by every event whose proposed circuit hashes identically.
+            let event_models: Vec<(AdminServiceEventModel, AdminEventCircuitProposalModel)> =
+                admin_service_event::table
+                    .filter(admin_service_event::id.eq_any(&event_ids))
+                    .inner_join(admin_event_circuit_proposal::table.on(
+                        admin_service_event::id.eq(admin_event_circuit_proposal::event_id),
+                    ))
+                    .load::<(AdminServiceEventModel, AdminEventCircuitProposalModel)>(self.conn)?;
+
+            let snapshot_hashes: Vec<String> = event_models
+                .iter()
+                .map(|(event_model, _)| event_model.circuit_snapshot_hash.clone())
+                .collect();
+
             // Transform previously-retrieved models into builders, keyed to the event ID
-            let events_map: HashMap<
-                i64,
-                (
-                    AdminServiceEventModel,
-                    CircuitProposalBuilder,
-                    ProposedCircuitBuilder,
-                ),
-            > = event_models
-                .into_iter()
-                .map(
-                    |(event_model, circuit_proposal_model, proposed_circuit_model)| {
+            let events_map: HashMap<i64, (AdminServiceEventModel, CircuitProposalBuilder)> =
+                event_models
+                    .into_iter()
+                    .map(|(event_model, circuit_proposal_model)| {
                         let proposal_builder = CircuitProposalBuilder::new()
                             .with_proposal_type(&ProposalType::try_from(
                                 circuit_proposal_model.proposal_type.to_string(),
@@ -102,172 +94,163 @@ where
                             .with_circuit_hash(&circuit_proposal_model.circuit_hash)
                             .with_requester(&circuit_proposal_model.requester)
                             .with_requester_node_id(&circuit_proposal_model.requester_node_id);
-                        let mut proposed_circuit_builder = ProposedCircuitBuilder::new()
-                            .with_circuit_id(&proposed_circuit_model.circuit_id)
-                            .with_authorization_type(&AuthorizationType::try_from(
-                                proposed_circuit_model.authorization_type,
-                            )?)
-                            .with_persistence(&PersistenceType::try_from(
-                                proposed_circuit_model.persistence,
-                            )?)
-                            .with_durability(&DurabilityType::try_from(
-                                proposed_circuit_model.durability,
-                            )?)
-                            .with_routes(&RouteType::try_from(proposed_circuit_model.routes)?)
-                            .with_circuit_management_type(
-                                &proposed_circuit_model.circuit_management_type,
-                            );
-                        if let Some(application_metadata) =
-                            &proposed_circuit_model.application_metadata
-                        {
-                            proposed_circuit_builder = proposed_circuit_builder
-                                .with_application_metadata(&application_metadata);
-                        }
 
-                        if let Some(comments) = &proposed_circuit_model.comments {
-                            proposed_circuit_builder =
-                                proposed_circuit_builder.with_comments(&comments);
-                        }
+                        Ok((event_model.id, (event_model, proposal_builder)))
+                    })
+                    .collect::<Result<HashMap<i64, (_, _)>, AdminServiceEventStoreError>>()?;
 
-                        if let Some(display_name) = &proposed_circuit_model.display_name {
-                            proposed_circuit_builder =
-                                proposed_circuit_builder.with_display_name(&display_name);
-                        }
+            // Each distinct snapshot is only built once, no matter how many events reference it
+            let mut snapshot_builders: HashMap<String, ProposedCircuitBuilder> = HashMap::new();
+            for snapshot_model in admin_event_circuit_snapshot::table
+                .filter(admin_event_circuit_snapshot::circuit_snapshot_hash.eq_any(&snapshot_hashes))
+                .load::<AdminEventCircuitSnapshotModel>(self.conn)?
+            {
+                let mut proposed_circuit_builder = ProposedCircuitBuilder::new()
+                    .with_circuit_id(&snapshot_model.circuit_id)
+                    .with_authorization_type(&AuthorizationType::try_from(
+                        snapshot_model.authorization_type,
+                    )?)
+                    .with_persistence(&PersistenceType::try_from(snapshot_model.persistence)?)
+                    .with_durability(&DurabilityType::try_from(snapshot_model.durability)?)
+                    .with_routes(&RouteType::try_from(snapshot_model.routes)?)
+                    .with_circuit_management_type(&snapshot_model.circuit_management_type);
 
-                        Ok((
-                            event_model.id,
-                            (event_model, proposal_builder, proposed_circuit_builder),
-                        ))
-                    },
-                )
-                .collect::<Result<HashMap<i64, (_, _, _)>, AdminServiceEventStoreError>>()?;
+                if let Some(application_metadata) = &snapshot_model.application_metadata {
+                    proposed_circuit_builder =
+                        proposed_circuit_builder.with_application_metadata(&application_metadata);
+                }
+
+                if let Some(comments) = &snapshot_model.comments {
+                    proposed_circuit_builder = proposed_circuit_builder.with_comments(&comments);
+                }
+
+                if let Some(display_name) = &snapshot_model.display_name {
+                    proposed_circuit_builder =
+                        proposed_circuit_builder.with_display_name(&display_name);
+                }
+
+                snapshot_builders.insert(snapshot_model.circuit_snapshot_hash, proposed_circuit_builder);
+            }
 
             // Collect `ProposedServices` to apply to the `ProposedCircuit`
-            // Create HashMap of (`event_id`, `service_id`) to a `ProposedServiceBuilder`
-            let mut proposed_services: HashMap<(i64, String), ProposedServiceBuilder> =
+            // Create HashMap of (`circuit_snapshot_hash`, `service_id`) to a `ProposedServiceBuilder`
+            let mut proposed_services: HashMap<(String, String), ProposedServiceBuilder> =
+                HashMap::new();
+            // Create HashMap of (`circuit_snapshot_hash`, `service_id`) to the associated argument values
+            let mut arguments_map: HashMap<(String, String), Vec<(String, String)>> =
                 HashMap::new();
-            // Create HashMap of (`event_id`, `service_id`) to the associated argument values
-            let mut arguments_map: HashMap<(i64, String), Vec<(String, String)>> = HashMap::new();
-            for (proposed_service, opt_arg) in admin_event_proposed_service::table
-                .filter(admin_event_proposed_service::event_id.eq_any(&event_ids))
+            for (proposed_service, opt_arg) in admin_event_circuit_snapshot_service::table
+                .filter(
+                    admin_event_circuit_snapshot_service::circuit_snapshot_hash
+                        .eq_any(&snapshot_hashes),
+                )
                 .left_join(
-                    admin_event_proposed_service_argument::table.on(
-                        admin_event_proposed_service::event_id
-                            .eq(admin_event_proposed_service_argument::event_id)
+                    admin_event_circuit_snapshot_service_argument::table.on(
+                        admin_event_circuit_snapshot_service::circuit_snapshot_hash
+                            .eq(admin_event_circuit_snapshot_service_argument::circuit_snapshot_hash)
                             .and(
-                                admin_event_proposed_service::service_id
-                                    .eq(admin_event_proposed_service_argument::service_id),
+                                admin_event_circuit_snapshot_service::service_id.eq(
+                                    admin_event_circuit_snapshot_service_argument::service_id,
+                                ),
                             ),
                     ),
                 )
                 .select((
-                    admin_event_proposed_service::all_columns,
-                    admin_event_proposed_service_argument::all_columns.nullable(),
+                    admin_event_circuit_snapshot_service::all_columns,
+                    admin_event_circuit_snapshot_service_argument::all_columns.nullable(),
                 ))
                 .load::<(
-                    AdminEventProposedServiceModel,
-                    Option<AdminEventProposedServiceArgumentModel>,
+                    AdminEventCircuitSnapshotServiceModel,
+                    Option<AdminEventCircuitSnapshotServiceArgumentModel>,
                 )>(self.conn)?
             {
+                let key = (
+                    proposed_service.circuit_snapshot_hash.clone(),
+                    proposed_service.service_id.to_string(),
+                );
                 if let Some(arg_model) = opt_arg {
-                    if let Some(args) = arguments_map.get_mut(&(
-                        proposed_service.event_id,
-                        proposed_service.service_id.to_string(),
-                    )) {
+                    if let Some(args) = arguments_map.get_mut(&key) {
                         args.push((arg_model.key.to_string(), arg_model.value.to_string()));
                     } else {
                         arguments_map.insert(
-                            (
-                                proposed_service.event_id,
-                                proposed_service.service_id.to_string(),
-                            ),
+                            key.clone(),
                             vec![(arg_model.key.to_string(), arg_model.value.to_string())],
                         );
                     }
                 }
                 // Insert new `ProposedServiceBuilder` if it does not already exist
-                proposed_services
-                    .entry((
-                        proposed_service.event_id,
-                        proposed_service.service_id.to_string(),
-                    ))
-                    .or_insert_with(|| {
-                        ProposedServiceBuilder::new()
-                            .with_service_id(&proposed_service.service_id)
-                            .with_service_type(&proposed_service.service_type)
-                            .with_node_id(&proposed_service.node_id)
-                    });
+                proposed_services.entry(key).or_insert_with(|| {
+                    ProposedServiceBuilder::new()
+                        .with_service_id(&proposed_service.service_id)
+                        .with_service_type(&proposed_service.service_type)
+                        .with_node_id(&proposed_service.node_id)
+                });
             }
-            // Need to collect the `ProposedServices` mapped to `event_ids`
-            let mut built_proposed_services: HashMap<i64, Vec<ProposedService>> = HashMap::new();
-            for ((event_id, service_id), mut builder) in proposed_services.into_iter() {
-                if let Some(args) = arguments_map.get(&(event_id, service_id.to_string())) {
+            // Need to collect the `ProposedServices` mapped to `circuit_snapshot_hash`
+            let mut built_proposed_services: HashMap<String, Vec<ProposedService>> =
+                HashMap::new();
+            for ((snapshot_hash, service_id), mut builder) in proposed_services.into_iter() {
+                if let Some(args) = arguments_map.get(&(snapshot_hash.clone(), service_id)) {
                     builder = builder.with_arguments(&args);
                 }
                 let proposed_service = builder
                     .build()
                     .map_err(AdminServiceEventStoreError::InvalidStateError)?;
 
-                if let Some(service_list) = built_proposed_services.get_mut(&event_id) {
-                    service_list.push(proposed_service);
-                } else {
-                    built_proposed_services.insert(event_id, vec![proposed_service]);
-                }
+                built_proposed_services
+                    .entry(snapshot_hash)
+                    .or_insert_with(Vec::new)
+                    .push(proposed_service);
             }
             // Collect `ProposedNodes` and proposed node endpoints
-            let mut proposed_nodes: HashMap<(i64, String), ProposedNodeBuilder> = HashMap::new();
-            for (node, endpoint) in admin_event_proposed_node::table
-                .filter(admin_event_proposed_node::event_id.eq_any(&event_ids))
+            let mut proposed_nodes: HashMap<(String, String), ProposedNodeBuilder> =
+                HashMap::new();
+            for (node, endpoint) in admin_event_circuit_snapshot_node::table
+                .filter(
+                    admin_event_circuit_snapshot_node::circuit_snapshot_hash
+                        .eq_any(&snapshot_hashes),
+                )
                 .inner_join(
-                    admin_event_proposed_node_endpoint::table.on(
-                        admin_event_proposed_node::node_id
-                            .eq(admin_event_proposed_node_endpoint::node_id)
+                    admin_event_circuit_snapshot_node_endpoint::table.on(
+                        admin_event_circuit_snapshot_node::node_id
+                            .eq(admin_event_circuit_snapshot_node_endpoint::node_id)
                             .and(
-                                admin_event_proposed_node_endpoint::event_id
-                                    .eq(admin_event_proposed_node::event_id),
+                                admin_event_circuit_snapshot_node_endpoint::circuit_snapshot_hash
+                                    .eq(admin_event_circuit_snapshot_node::circuit_snapshot_hash),
                             ),
                     ),
                 )
                 .select((
-                    admin_event_proposed_node::all_columns,
-                    admin_event_proposed_node_endpoint::endpoint,
+                    admin_event_circuit_snapshot_node::all_columns,
+                    admin_event_circuit_snapshot_node_endpoint::endpoint,
                 ))
-                .load::<(AdminEventProposedNodeModel, String)>(self.conn)?
+                .load::<(AdminEventCircuitSnapshotNodeModel, String)>(self.conn)?
             {
-                if let Some(proposed_node) =
-                    proposed_nodes.remove(&(node.event_id, node.node_id.to_string()))
-                {
+                let key = (node.circuit_snapshot_hash.clone(), node.node_id.to_string());
+                if let Some(proposed_node) = proposed_nodes.remove(&key) {
                     if let Some(mut endpoints) = proposed_node.endpoints() {
                         endpoints.push(endpoint);
-                        let proposed_node = proposed_node.with_endpoints(&endpoints);
-                        proposed_nodes.insert((node.event_id, node.node_id), proposed_node);
+                        proposed_nodes.insert(key, proposed_node.with_endpoints(&endpoints));
                     } else {
-                        let proposed_node = proposed_node.with_endpoints(&[endpoint]);
-                        proposed_nodes.insert((node.event_id, node.node_id), proposed_node);
+                        proposed_nodes.insert(key, proposed_node.with_endpoints(&[endpoint]));
                     }
                 } else {
                     let proposed_node = ProposedNodeBuilder::new()
                         .with_node_id(&node.node_id)
                         .with_endpoints(&[endpoint]);
-                    proposed_nodes.insert((node.event_id, node.node_id), proposed_node);
+                    proposed_nodes.insert(key, proposed_node);
                 }
             }
-            let mut built_proposed_nodes: HashMap<i64, Vec<ProposedNode>> = HashMap::new();
-            for ((event_id, _), builder) in proposed_nodes.into_iter() {
-                if let Some(nodes) = built_proposed_nodes.get_mut(&event_id) {
-                    nodes.push(
+            let mut built_proposed_nodes: HashMap<String, Vec<ProposedNode>> = HashMap::new();
+            for ((snapshot_hash, _), builder) in proposed_nodes.into_iter() {
+                built_proposed_nodes
+                    .entry(snapshot_hash)
+                    .or_insert_with(Vec::new)
+                    .push(
                         builder
                             .build()
                             .map_err(AdminServiceEventStoreError::InvalidStateError)?,
-                    )
-                } else {
-                    built_proposed_nodes.insert(
-                        event_id,
-                        vec![builder
-                            .build()
-                            .map_err(AdminServiceEventStoreError::InvalidStateError)?],
                     );
-                }
             }
 
             // Collect votes to apply to the 'CircuitProposal'
@@ -292,13 +275,22 @@ where
             }
 
             let mut events: Vec<AdminServiceEvent> = Vec::new();
-            for (event_id, (event_model, mut proposal_builder, mut proposed_circuit_builder)) in
-                events_map
-            {
-                if let Some(services) = built_proposed_services.get(&event_id) {
+            for (event_id, (event_model, mut proposal_builder)) in events_map {
+                let mut proposed_circuit_builder = snapshot_builders
+                    .get(&event_model.circuit_snapshot_hash)
+                    .cloned()
+                    .ok_or_else(|| {
+                        AdminServiceEventStoreError::InvalidStateError(
+                            InvalidStateError::with_message(format!(
+                                "no circuit snapshot found for hash {}",
+                                event_model.circuit_snapshot_hash
+                            )),
+                        )
+                    })?;
+                if let Some(services) = built_proposed_services.get(&event_model.circuit_snapshot_hash) {
                     proposed_circuit_builder = proposed_circuit_builder.with_roster(&services);
                 }
-                if let Some(nodes) = built_proposed_nodes.get(&event_id) {
+                if let Some(nodes) = built_proposed_nodes.get(&event_model.circuit_snapshot_hash) {
                     proposed_circuit_builder = proposed_circuit_builder.with_members(nodes);
                 }
                 if let Some(votes) = vote_records.get(&event_id) {
@@ -0,0 +1,114 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Used by operations to retrieve a bounded, ordered page of `AdminServiceEvent` instances that
+//! follow a given event ID, optionally filtered by circuit management type and proposal type.
+
+use diesel::{prelude::*, types::HasSqlType};
+
+use super::list_events::AdminServiceEventStoreListEventsOperation;
+use super::AdminServiceEventStoreOperations;
+
+use crate::admin::service::event::{
+    store::{
+        diesel::schema::{admin_event_circuit_proposal, admin_event_circuit_snapshot, admin_service_event},
+        AdminServiceEventStoreError,
+    },
+    AdminServiceEvent,
+};
+use crate::admin::store::ProposalType;
+
+/// A bounded window of events, along with the cursor (the ID of the last event in the page) that
+/// a caller can pass back in as `start_id` to fetch the next page.
+pub struct EventPage {
+    pub events: Vec<AdminServiceEvent>,
+    pub next_cursor: Option<i64>,
+}
+
+pub(in crate::admin::service::event::store::diesel) trait AdminServiceEventStoreListEventsSinceOperation
+{
+    /// Returns up to `limit` events after `start_id`, optionally narrowed to a
+    /// `circuit_management_type` and/or a `proposal_type`.
+    ///
+    /// `proposal_type` filters on the proposal's *kind* (`Create`, `UpdateRoster`, `AddNode`,
+    /// `RemoveNode`, `Destroy`), not on vote/acceptance status -- this store holds no
+    /// vote-tally or acceptance-policy state to filter on, only the committed event log.
+    fn list_events_since(
+        &self,
+        start_id: i64,
+        limit: usize,
+        circuit_management_type: Option<&str>,
+        proposal_type: Option<&ProposalType>,
+    ) -> Result<EventPage, AdminServiceEventStoreError>;
+}
+
+impl<'a, C> AdminServiceEventStoreListEventsSinceOperation for AdminServiceEventStoreOperations<'a, C>
+where
+    C: diesel::Connection,
+    C::Backend: HasSqlType<diesel::sql_types::BigInt>,
+    String: diesel::deserialize::FromSql<diesel::sql_types::Text, C::Backend>,
+    i64: diesel::deserialize::FromSql<diesel::sql_types::BigInt, C::Backend>,
+    Vec<u8>: diesel::deserialize::FromSql<diesel::sql_types::Binary, C::Backend>,
+{
+    fn list_events_since(
+        &self,
+        start_id: i64,
+        limit: usize,
+        circuit_management_type: Option<&str>,
+        proposal_type: Option<&ProposalType>,
+    ) -> Result<EventPage, AdminServiceEventStoreError> {
+        self.conn.transaction::<EventPage, _, _>(|| {
+            // `circuit_management_type` no longer lives on a per-event row: it is carried by the
+            // shared `admin_event_circuit_snapshot` row that `circuit_snapshot_hash` points at,
+            // so the filter joins through the hash rather than `event_id`.
+            let mut query = admin_service_event::table
+                .inner_join(
+                    admin_event_circuit_proposal::table
+                        .on(admin_service_event::id.eq(admin_event_circuit_proposal::event_id)),
+                )
+                .inner_join(
+                    admin_event_circuit_snapshot::table.on(admin_service_event::circuit_snapshot_hash
+                        .eq(admin_event_circuit_snapshot::circuit_snapshot_hash)),
+                )
+                .filter(admin_service_event::id.gt(start_id))
+                .into_boxed();
+
+            if let Some(circuit_management_type) = circuit_management_type {
+                query = query.filter(
+                    admin_event_circuit_snapshot::circuit_management_type
+                        .eq(circuit_management_type.to_string()),
+                );
+            }
+
+            if let Some(proposal_type) = proposal_type {
+                query = query
+                    .filter(admin_event_circuit_proposal::proposal_type.eq(proposal_type.to_string()));
+            }
+
+            // Only a bounded window of IDs is ever materialized here; the full builders are
+            // reconstructed by `list_events`, which already knows how to join and build them.
+            let event_ids: Vec<i64> = query
+                .order(admin_service_event::id.asc())
+                .limit(limit as i64)
+                .select(admin_service_event::id)
+                .load::<i64>(self.conn)?;
+
+            let next_cursor = event_ids.last().copied();
+
+            let events: Vec<AdminServiceEvent> = self.list_events(event_ids)?.collect();
+
+            Ok(EventPage { events, next_cursor })
+        })
+    }
+}
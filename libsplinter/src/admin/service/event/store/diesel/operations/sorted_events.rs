@@ -0,0 +1,296 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic, node-independent ordering of `AdminServiceEvent`s.
+//!
+//! `list_events` sorts its result by `event_id`, a node-local autoincrement that carries no
+//! meaning once event logs from multiple nodes are reconciled or displayed side by side. This
+//! module adds a `list_sorted_events` operation that instead derives a global order from the
+//! proposal dependency DAG. The edges of that DAG are read straight off
+//! `admin_event_circuit_proposal`: each row's `previous_circuit_hash` names the `circuit_hash` of
+//! the proposal it extends (`NULL` for a circuit's first proposal), so the DAG is built entirely
+//! from committed, content-addressed data rather than from `event_id` or anything reconstructed
+//! through a builder.
+//!
+//! The DAG is ordered with Kahn's algorithm. Rather than popping an arbitrary zero-in-degree
+//! node, a binary heap pops the *lexicographically least* ready node, keyed on
+//! `(proposal_type rank, requester_node_id, circuit_id, circuit_hash)`, so two nodes computing
+//! the same DAG always produce the same order. Any rows left with nonzero in-degree once the
+//! queue drains indicate a cycle and are surfaced as an `InvalidStateError`.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use diesel::{prelude::*, types::HasSqlType};
+
+use super::list_events::AdminServiceEventStoreListEventsOperation;
+use super::AdminServiceEventStoreOperations;
+
+use crate::admin::service::event::{
+    store::{
+        diesel::{models::AdminEventCircuitProposalModel, schema::admin_event_circuit_proposal},
+        AdminServiceEventStoreError, EventIter,
+    },
+    AdminServiceEvent,
+};
+use crate::error::InvalidStateError;
+
+pub(in crate::admin::service::event::store::diesel) trait AdminServiceEventStoreSortedEventsOperation
+{
+    /// Returns every event referenced by `event_ids`, ordered by the deterministic global order
+    /// described above rather than by `event_id`.
+    fn list_sorted_events(
+        &self,
+        event_ids: Vec<i64>,
+    ) -> Result<EventIter, AdminServiceEventStoreError>;
+}
+
+impl<'a, C> AdminServiceEventStoreSortedEventsOperation for AdminServiceEventStoreOperations<'a, C>
+where
+    C: diesel::Connection,
+    C::Backend: HasSqlType<diesel::sql_types::BigInt>,
+    String: diesel::deserialize::FromSql<diesel::sql_types::Text, C::Backend>,
+    i64: diesel::deserialize::FromSql<diesel::sql_types::BigInt, C::Backend>,
+    Vec<u8>: diesel::deserialize::FromSql<diesel::sql_types::Binary, C::Backend>,
+    Self: AdminServiceEventStoreListEventsOperation,
+{
+    fn list_sorted_events(
+        &self,
+        event_ids: Vec<i64>,
+    ) -> Result<EventIter, AdminServiceEventStoreError> {
+        let proposal_rows: Vec<AdminEventCircuitProposalModel> = admin_event_circuit_proposal::table
+            .filter(admin_event_circuit_proposal::event_id.eq_any(&event_ids))
+            .load::<AdminEventCircuitProposalModel>(self.conn)?;
+
+        let ordered_ids = topological_order(&proposal_rows)?;
+
+        let mut events_by_id: HashMap<i64, AdminServiceEvent> = self
+            .list_events(event_ids)?
+            .map(|event| (event.event_id, event))
+            .collect();
+
+        let ordered_events = ordered_ids
+            .into_iter()
+            .filter_map(|event_id| events_by_id.remove(&event_id))
+            .collect::<Vec<_>>();
+
+        Ok(Box::new(ordered_events.into_iter()))
+    }
+}
+
+/// A rank assigned to each `proposal_type` purely to make the tie-break key total and stable; the
+/// specific numbering is arbitrary but must never change once events have been ordered with it.
+fn proposal_type_rank(proposal_type: &str) -> u8 {
+    match proposal_type {
+        "Create" => 0,
+        "UpdateRoster" => 1,
+        "AddNode" => 2,
+        "RemoveNode" => 3,
+        "Destroy" => 4,
+        _ => u8::MAX,
+    }
+}
+
+/// The deterministic tie-break key used to choose among DAG nodes with equal in-degree.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct TieBreakKey {
+    proposal_type_rank: u8,
+    requester_node_id: String,
+    circuit_id: String,
+    circuit_hash: String,
+}
+
+impl TieBreakKey {
+    fn for_row(row: &AdminEventCircuitProposalModel) -> Self {
+        TieBreakKey {
+            proposal_type_rank: proposal_type_rank(&row.proposal_type),
+            requester_node_id: row.requester_node_id.clone(),
+            circuit_id: row.circuit_id.clone(),
+            circuit_hash: row.circuit_hash.clone(),
+        }
+    }
+}
+
+/// A heap entry ordered by `TieBreakKey` ascending. `BinaryHeap` is a max-heap, so `Ord` is
+/// reversed to make `ready.pop()` return the lexicographically least ready node.
+struct ReadyNode {
+    key: TieBreakKey,
+    index: usize,
+}
+
+impl PartialEq for ReadyNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for ReadyNode {}
+
+impl PartialOrd for ReadyNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ReadyNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.key.cmp(&self.key)
+    }
+}
+
+/// Runs Kahn's algorithm over `rows`' `previous_circuit_hash` edges and returns the event IDs in
+/// deterministic dependency order.
+fn topological_order(
+    rows: &[AdminEventCircuitProposalModel],
+) -> Result<Vec<i64>, AdminServiceEventStoreError> {
+    // Map each proposal's own circuit_hash to its index so dependents can be found by the hash
+    // they extend, never by event_id.
+    let hash_to_index: HashMap<&str, usize> = rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| (row.circuit_hash.as_str(), index))
+        .collect();
+
+    // dependents[i] holds the indexes of rows whose `previous_circuit_hash` points at rows[i]
+    let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); rows.len()];
+    let mut in_degree: Vec<usize> = vec![0; rows.len()];
+    for (index, row) in rows.iter().enumerate() {
+        if let Some(previous_hash) = row.previous_circuit_hash.as_deref() {
+            if let Some(&parent_index) = hash_to_index.get(previous_hash) {
+                dependents[parent_index].push(index);
+                in_degree[index] += 1;
+            }
+        }
+    }
+
+    let mut ready: BinaryHeap<ReadyNode> = in_degree
+        .iter()
+        .enumerate()
+        .filter(|(_, &degree)| degree == 0)
+        .map(|(index, _)| ReadyNode {
+            key: TieBreakKey::for_row(&rows[index]),
+            index,
+        })
+        .collect();
+
+    let mut order = Vec::with_capacity(rows.len());
+    let mut emitted = vec![false; rows.len()];
+    while let Some(ReadyNode { index, .. }) = ready.pop() {
+        order.push(index);
+        emitted[index] = true;
+        for &dependent in &dependents[index] {
+            in_degree[dependent] -= 1;
+            if in_degree[dependent] == 0 {
+                ready.push(ReadyNode {
+                    key: TieBreakKey::for_row(&rows[dependent]),
+                    index: dependent,
+                });
+            }
+        }
+    }
+
+    if order.len() != rows.len() {
+        let unresolved: Vec<&str> = emitted
+            .iter()
+            .enumerate()
+            .filter(|(_, &was_emitted)| !was_emitted)
+            .map(|(index, _)| rows[index].circuit_id.as_str())
+            .collect();
+        return Err(AdminServiceEventStoreError::InvalidStateError(
+            InvalidStateError::with_message(format!(
+                "cycle detected in proposal dependency DAG involving circuit(s): {}",
+                unresolved.join(", ")
+            )),
+        ));
+    }
+
+    Ok(order.into_iter().map(|index| rows[index].event_id).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(
+        event_id: i64,
+        proposal_type: &str,
+        requester_node_id: &str,
+        circuit_id: &str,
+        circuit_hash: &str,
+        previous_circuit_hash: Option<&str>,
+    ) -> AdminEventCircuitProposalModel {
+        AdminEventCircuitProposalModel {
+            event_id,
+            proposal_type: proposal_type.to_string(),
+            circuit_id: circuit_id.to_string(),
+            circuit_hash: circuit_hash.to_string(),
+            previous_circuit_hash: previous_circuit_hash.map(|hash| hash.to_string()),
+            requester: b"requester".to_vec(),
+            requester_node_id: requester_node_id.to_string(),
+        }
+    }
+
+    /// A straight chain must come out in dependency order regardless of the order the rows were
+    /// loaded in.
+    #[test]
+    fn orders_a_chain_by_dependency() {
+        let rows = vec![
+            row(3, "Destroy", "node-1", "circuit-1", "hash-3", Some("hash-2")),
+            row(1, "Create", "node-1", "circuit-1", "hash-1", None),
+            row(2, "UpdateRoster", "node-1", "circuit-1", "hash-2", Some("hash-1")),
+        ];
+
+        assert_eq!(topological_order(&rows).unwrap(), vec![1, 2, 3]);
+    }
+
+    /// Two independent roots (no edge between them) are ordered by the tie-break key, never by
+    /// `event_id`: here `Create` outranks `UpdateRoster` regardless of which event_id arrived
+    /// first.
+    #[test]
+    fn breaks_ties_by_proposal_type_rank_not_event_id() {
+        let rows = vec![
+            row(1, "UpdateRoster", "node-1", "circuit-1", "hash-1", None),
+            row(2, "Create", "node-1", "circuit-2", "hash-2", None),
+        ];
+
+        assert_eq!(topological_order(&rows).unwrap(), vec![2, 1]);
+    }
+
+    /// With proposal type tied, `requester_node_id` breaks the tie lexicographically.
+    #[test]
+    fn breaks_ties_by_requester_node_id() {
+        let rows = vec![
+            row(1, "Create", "node-b", "circuit-1", "hash-1", None),
+            row(2, "Create", "node-a", "circuit-2", "hash-2", None),
+        ];
+
+        assert_eq!(topological_order(&rows).unwrap(), vec![2, 1]);
+    }
+
+    /// A cycle (two rows each naming the other as predecessor) can never drain the ready queue,
+    /// and must surface as an `InvalidStateError` rather than silently dropping the involved
+    /// events.
+    #[test]
+    fn detects_a_cycle() {
+        let rows = vec![
+            row(1, "Create", "node-1", "circuit-1", "hash-1", Some("hash-2")),
+            row(2, "UpdateRoster", "node-1", "circuit-1", "hash-2", Some("hash-1")),
+        ];
+
+        match topological_order(&rows) {
+            Err(AdminServiceEventStoreError::InvalidStateError(_)) => (),
+            other => panic!("expected InvalidStateError, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Diesel table definitions backing the admin service event store.
+
+table! {
+    admin_service_event (id) {
+        id -> BigInt,
+        event_type -> Text,
+        circuit_snapshot_hash -> Text,
+        timestamp -> BigInt,
+    }
+}
+
+table! {
+    admin_event_circuit_proposal (event_id) {
+        event_id -> BigInt,
+        proposal_type -> Text,
+        circuit_id -> Text,
+        circuit_hash -> Text,
+        // The `circuit_hash` of the proposal this one extends; `NULL` for a circuit's first
+        // proposal. Forms the edges of the proposal dependency DAG used to derive a
+        // deterministic, node-independent event ordering.
+        previous_circuit_hash -> Nullable<Text>,
+        requester -> Binary,
+        requester_node_id -> Text,
+    }
+}
+
+table! {
+    admin_event_vote_record (id) {
+        id -> BigInt,
+        event_id -> BigInt,
+        public_key -> Binary,
+        vote -> Text,
+        voter_node_id -> Text,
+    }
+}
+
+table! {
+    // Keyed by `circuit_snapshot_hash` rather than `event_id`: every event whose proposed
+    // circuit hashes identically shares a single row here.
+    admin_event_circuit_snapshot (circuit_snapshot_hash) {
+        circuit_snapshot_hash -> Text,
+        circuit_id -> Text,
+        authorization_type -> Text,
+        persistence -> Text,
+        durability -> Text,
+        routes -> Text,
+        circuit_management_type -> Text,
+        application_metadata -> Nullable<Binary>,
+        comments -> Nullable<Text>,
+        display_name -> Nullable<Text>,
+    }
+}
+
+table! {
+    admin_event_circuit_snapshot_service (circuit_snapshot_hash, service_id) {
+        circuit_snapshot_hash -> Text,
+        service_id -> Text,
+        service_type -> Text,
+        node_id -> Text,
+    }
+}
+
+table! {
+    admin_event_circuit_snapshot_service_argument (circuit_snapshot_hash, service_id, key) {
+        circuit_snapshot_hash -> Text,
+        service_id -> Text,
+        key -> Text,
+        value -> Text,
+    }
+}
+
+table! {
+    admin_event_circuit_snapshot_node (circuit_snapshot_hash, node_id) {
+        circuit_snapshot_hash -> Text,
+        node_id -> Text,
+    }
+}
+
+table! {
+    admin_event_circuit_snapshot_node_endpoint (circuit_snapshot_hash, node_id, endpoint) {
+        circuit_snapshot_hash -> Text,
+        node_id -> Text,
+        endpoint -> Text,
+    }
+}
+
+joinable!(admin_event_circuit_proposal -> admin_service_event (event_id));
+joinable!(admin_event_vote_record -> admin_service_event (event_id));
+
+allow_tables_to_appear_in_same_query!(
+    admin_service_event,
+    admin_event_circuit_proposal,
+    admin_event_vote_record,
+    admin_event_circuit_snapshot,
+    admin_event_circuit_snapshot_service,
+    admin_event_circuit_snapshot_service_argument,
+    admin_event_circuit_snapshot_node,
+    admin_event_circuit_snapshot_node_endpoint,
+);
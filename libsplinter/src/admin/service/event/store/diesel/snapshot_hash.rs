@@ -0,0 +1,207 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Content-addressing for proposed-circuit snapshots.
+//!
+//! Most events in a circuit's lifecycle (vote after vote) carry a nearly identical
+//! `ProposedCircuit`. Rather than storing a full copy on every `AdminServiceEvent` row, the
+//! canonical inputs to a `ProposedCircuitBuilder` are hashed into a `CircuitSnapshotHash`, and the
+//! snapshot is persisted once in `admin_event_circuit_snapshot`, keyed by that hash, with
+//! `admin_service_event` rows simply referencing it.
+
+use openssl::hash::{hash, MessageDigest};
+
+use crate::admin::store::{ProposedCircuit, ProposedNode, ProposedService};
+
+/// A hex-encoded SHA-256 digest identifying a unique `ProposedCircuit` snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CircuitSnapshotHash(String);
+
+impl CircuitSnapshotHash {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for CircuitSnapshotHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Computes the stable content hash for a `ProposedCircuit`: circuit ID, auth/persistence/
+/// durability/route types, the sorted roster, members (with endpoints), and arguments, and
+/// `application_metadata`/`comments`/`display_name`. Sorting the roster, members, and arguments
+/// before hashing ensures two snapshots that differ only in the order their nodes/services were
+/// added hash identically. The metadata/comments/display name fields are included precisely so a
+/// resubmission that only changes one of them is treated as a new snapshot instead of silently
+/// reusing (and keeping) another submission's stale values.
+pub fn hash_proposed_circuit(circuit: &ProposedCircuit) -> CircuitSnapshotHash {
+    let digest = hash(MessageDigest::sha256(), canonicalize(circuit).as_bytes())
+        .expect("openssl sha256 is always available");
+    CircuitSnapshotHash(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn canonicalize(circuit: &ProposedCircuit) -> String {
+    let mut roster: Vec<String> = circuit.roster().iter().map(canonicalize_service).collect();
+    roster.sort();
+
+    let mut members: Vec<String> = circuit.members().iter().map(canonicalize_node).collect();
+    members.sort();
+
+    format!(
+        "{}\u{1}{:?}\u{1}{:?}\u{1}{:?}\u{1}{:?}\u{1}{}\u{1}{}\u{1}{}\u{1}{:?}\u{1}{:?}\u{1}{:?}",
+        circuit.circuit_id(),
+        circuit.authorization_type(),
+        circuit.persistence(),
+        circuit.durability(),
+        circuit.routes(),
+        circuit.circuit_management_type(),
+        roster.join("\u{2}"),
+        members.join("\u{2}"),
+        circuit.application_metadata(),
+        circuit.comments(),
+        circuit.display_name(),
+    )
+}
+
+fn canonicalize_service(service: &ProposedService) -> String {
+    let mut arguments: Vec<(String, String)> = service.arguments().to_vec();
+    arguments.sort();
+    format!(
+        "{}\u{3}{}\u{3}{}\u{3}{:?}",
+        service.service_id(),
+        service.service_type(),
+        service.node_id(),
+        arguments,
+    )
+}
+
+fn canonicalize_node(node: &ProposedNode) -> String {
+    let mut endpoints: Vec<String> = node.endpoints().to_vec();
+    endpoints.sort();
+    format!("{}\u{3}{:?}", node.node_id(), endpoints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::admin::store::{
+        AuthorizationType, DurabilityType, PersistenceType, ProposedCircuitBuilder,
+        ProposedNodeBuilder, ProposedServiceBuilder, RouteType,
+    };
+
+    fn circuit_builder() -> ProposedCircuitBuilder {
+        ProposedCircuitBuilder::new()
+            .with_circuit_id("circuit-1")
+            .with_authorization_type(&AuthorizationType::Trust)
+            .with_persistence(&PersistenceType::Accept)
+            .with_durability(&DurabilityType::NoDurability)
+            .with_routes(&RouteType::Any)
+            .with_circuit_management_type("test-app")
+    }
+
+    fn service(service_id: &str, node_id: &str) -> crate::admin::store::ProposedService {
+        ProposedServiceBuilder::new()
+            .with_service_id(service_id)
+            .with_service_type("scabbard")
+            .with_node_id(node_id)
+            .with_arguments(&[("key".into(), "value".into())])
+            .build()
+            .expect("failed to build service")
+    }
+
+    fn node(node_id: &str) -> crate::admin::store::ProposedNode {
+        ProposedNodeBuilder::new()
+            .with_node_id(node_id)
+            .with_endpoints(&[format!("tcp://{}:8080", node_id)])
+            .build()
+            .expect("failed to build node")
+    }
+
+    /// Two snapshots whose roster/members were only added in a different order must hash
+    /// identically.
+    #[test]
+    fn hash_is_order_independent() {
+        let services_a = vec![service("service-a", "node-1"), service("service-b", "node-2")];
+        let services_b = vec![service("service-b", "node-2"), service("service-a", "node-1")];
+        let members_a = vec![node("node-1"), node("node-2")];
+        let members_b = vec![node("node-2"), node("node-1")];
+
+        let circuit_a = circuit_builder()
+            .with_roster(&services_a)
+            .with_members(&members_a)
+            .build()
+            .expect("failed to build circuit");
+        let circuit_b = circuit_builder()
+            .with_roster(&services_b)
+            .with_members(&members_b)
+            .build()
+            .expect("failed to build circuit");
+
+        assert_eq!(
+            hash_proposed_circuit(&circuit_a),
+            hash_proposed_circuit(&circuit_b)
+        );
+    }
+
+    /// A resubmission that only changes `comments`, `display_name`, or `application_metadata`
+    /// must hash differently, or a content-hash hit would silently keep the first submission's
+    /// stale values forever.
+    #[test]
+    fn hash_changes_with_comments_display_name_and_metadata() {
+        let base = circuit_builder()
+            .with_roster(&[service("service-a", "node-1")])
+            .with_members(&[node("node-1")])
+            .build()
+            .expect("failed to build circuit");
+
+        let with_comments = circuit_builder()
+            .with_roster(&[service("service-a", "node-1")])
+            .with_members(&[node("node-1")])
+            .with_comments("updated comments")
+            .build()
+            .expect("failed to build circuit");
+
+        assert_ne!(
+            hash_proposed_circuit(&base),
+            hash_proposed_circuit(&with_comments)
+        );
+
+        let with_display_name = circuit_builder()
+            .with_roster(&[service("service-a", "node-1")])
+            .with_members(&[node("node-1")])
+            .with_display_name("updated display name")
+            .build()
+            .expect("failed to build circuit");
+
+        assert_ne!(
+            hash_proposed_circuit(&base),
+            hash_proposed_circuit(&with_display_name)
+        );
+
+        let with_metadata = circuit_builder()
+            .with_roster(&[service("service-a", "node-1")])
+            .with_members(&[node("node-1")])
+            .with_application_metadata(&b"updated metadata"[..])
+            .build()
+            .expect("failed to build circuit");
+
+        assert_ne!(
+            hash_proposed_circuit(&base),
+            hash_proposed_circuit(&with_metadata)
+        );
+    }
+}
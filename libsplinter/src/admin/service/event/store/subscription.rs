@@ -0,0 +1,301 @@
+// Copyright 2018-2020 Cargill Incorporated
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Push-based subscriptions over committed `AdminServiceEvent`s.
+//!
+//! Today the only way to observe admin events is to repeatedly call `list_events` with freshly
+//! discovered IDs. `AdminServiceEventSubscriberFactory` replaces that poll loop with a single
+//! `subscribe` call that first replays persisted events after a cursor and then tails newly
+//! committed events as the store's write path reports them.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+
+use super::{AdminServiceEventStoreError, EventIter};
+use crate::admin::service::event::AdminServiceEvent;
+
+/// Bounded capacity of a subscriber's channel. A subscriber that cannot keep up with this many
+/// buffered events is dropped rather than allowed to back up the write path.
+const SUBSCRIBER_CHANNEL_CAPACITY: usize = 256;
+
+/// Delivered to a subscriber once its channel has filled and it has been dropped, carrying the
+/// cursor it should pass back to `subscribe` to pick up where it left off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventSubscriberLagged {
+    pub resume_from: i64,
+}
+
+/// A single item delivered over an `EventStream`.
+#[derive(Debug)]
+pub enum SubscriptionEvent {
+    Event(AdminServiceEvent),
+    Lagged(EventSubscriberLagged),
+}
+
+/// A stream that replays persisted events and then tails newly committed ones, ending with a
+/// `SubscriptionEvent::Lagged` if the subscriber ever fell behind.
+pub type EventStream = Box<dyn Iterator<Item = SubscriptionEvent> + Send>;
+
+struct Subscriber {
+    sender: SyncSender<AdminServiceEvent>,
+    lagged: Arc<AtomicBool>,
+}
+
+/// Fans committed `AdminServiceEvent`s out to every live subscription. A single instance is
+/// shared by the store's write path, which calls `notify` immediately after each event is
+/// committed, and by every call to `subscribe`.
+#[derive(Default)]
+pub struct AdminServiceEventSubscriberFactory {
+    subscribers: Mutex<Vec<Subscriber>>,
+}
+
+impl AdminServiceEventSubscriberFactory {
+    pub fn new() -> Self {
+        AdminServiceEventSubscriberFactory {
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Called by the store's write path once an `AdminServiceEvent` has been committed. A
+    /// subscriber whose channel is already full has fallen behind; rather than block the writer
+    /// or buffer unboundedly on its behalf, it is marked lagged and dropped.
+    pub fn notify(&self, event: &AdminServiceEvent) {
+        let mut subscribers = self.subscribers.lock().expect("subscriber lock poisoned");
+        subscribers.retain(|subscriber| match subscriber.sender.try_send(event.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                subscriber.lagged.store(true, Ordering::SeqCst);
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+
+    /// Registers a live subscription and replays persisted events after `since` (via `replay`,
+    /// which reuses the store's existing build logic). The returned stream yields replayed
+    /// events, then live events, and finally a single `Lagged` item carrying a resume cursor if
+    /// the subscriber was ever dropped for falling behind.
+    ///
+    /// The subscriber is registered *before* `replay` runs, not after: an event committed while
+    /// the replay query is executing would otherwise fall in the gap between the two steps,
+    /// delivered neither by the replay (it post-dates the query) nor live (the subscriber
+    /// wasn't registered yet when `notify` ran), and be lost silently. Registering first means
+    /// such an event instead arrives on the live channel, possibly duplicating one already
+    /// covered by the replay; the live stream skips anything at or below the replay's cursor to
+    /// compensate.
+    pub fn subscribe<F>(
+        &self,
+        since: Option<i64>,
+        replay: F,
+    ) -> Result<EventStream, AdminServiceEventStoreError>
+    where
+        F: FnOnce(i64) -> Result<EventIter, AdminServiceEventStoreError>,
+    {
+        let (sender, receiver) = sync_channel(SUBSCRIBER_CHANNEL_CAPACITY);
+        let lagged = Arc::new(AtomicBool::new(false));
+        self.subscribers
+            .lock()
+            .expect("subscriber lock poisoned")
+            .push(Subscriber {
+                sender,
+                lagged: lagged.clone(),
+            });
+
+        let replayed: Vec<AdminServiceEvent> = match replay(since.unwrap_or(0)) {
+            Ok(events) => events.collect(),
+            Err(err) => {
+                // The replay failed, so this subscription never happened; drop the slot we
+                // reserved for it rather than leaking a subscriber no caller holds a stream for.
+                self.subscribers
+                    .lock()
+                    .expect("subscriber lock poisoned")
+                    .retain(|subscriber| !Arc::ptr_eq(&subscriber.lagged, &lagged));
+                return Err(err);
+            }
+        };
+        let last_seen_id = replayed
+            .last()
+            .map(|event| event.event_id)
+            .or(since)
+            .unwrap_or(0);
+
+        let live = LiveSubscription {
+            receiver,
+            lagged,
+            last_seen_id,
+            emitted_lag: false,
+        };
+
+        Ok(Box::new(
+            replayed.into_iter().map(SubscriptionEvent::Event).chain(live),
+        ))
+    }
+}
+
+/// Tails a subscriber's channel, tracking the last-seen event ID both to skip anything already
+/// delivered via replay (or already delivered live) and so that a `Lagged` signal can carry an
+/// accurate resume cursor.
+struct LiveSubscription {
+    receiver: Receiver<AdminServiceEvent>,
+    lagged: Arc<AtomicBool>,
+    last_seen_id: i64,
+    emitted_lag: bool,
+}
+
+impl Iterator for LiveSubscription {
+    type Item = SubscriptionEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.emitted_lag {
+            return None;
+        }
+        loop {
+            match self.receiver.recv() {
+                Ok(event) => {
+                    // Registering before replay means an event committed in between can arrive
+                    // here already covered by the replayed batch; skip it rather than deliver it
+                    // twice.
+                    if event.event_id <= self.last_seen_id {
+                        continue;
+                    }
+                    self.last_seen_id = event.event_id;
+                    return Some(SubscriptionEvent::Event(event));
+                }
+                Err(_) if self.lagged.load(Ordering::SeqCst) => {
+                    self.emitted_lag = true;
+                    return Some(SubscriptionEvent::Lagged(EventSubscriberLagged {
+                        resume_from: self.last_seen_id,
+                    }));
+                }
+                Err(_) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::convert::TryFrom;
+
+    use crate::admin::service::event::store::diesel::models::AdminServiceEventModel;
+    use crate::admin::store::{
+        AuthorizationType, CircuitProposalBuilder, DurabilityType, PersistenceType, ProposalType,
+        ProposedCircuitBuilder, RouteType,
+    };
+
+    fn sample_event(event_id: i64) -> AdminServiceEvent {
+        let circuit = ProposedCircuitBuilder::new()
+            .with_circuit_id("circuit-1")
+            .with_authorization_type(&AuthorizationType::Trust)
+            .with_persistence(&PersistenceType::Accept)
+            .with_durability(&DurabilityType::NoDurability)
+            .with_routes(&RouteType::Any)
+            .with_circuit_management_type("test-app")
+            .build()
+            .expect("failed to build circuit");
+
+        let proposal = CircuitProposalBuilder::new()
+            .with_proposal_type(&ProposalType::Create)
+            .with_circuit_id("circuit-1")
+            .with_circuit_hash("circuit-hash")
+            .with_requester(b"requester")
+            .with_requester_node_id("node-1")
+            .with_circuit(&circuit)
+            .build()
+            .expect("failed to build proposal");
+
+        let model = AdminServiceEventModel {
+            id: event_id,
+            event_type: "ProposalSubmitted".into(),
+            circuit_snapshot_hash: "snapshot-hash".into(),
+            timestamp: 0,
+        };
+
+        AdminServiceEvent::try_from((model, proposal)).expect("failed to build event")
+    }
+
+    fn no_replay(_since: i64) -> Result<EventIter, AdminServiceEventStoreError> {
+        Ok(Box::new(std::iter::empty()))
+    }
+
+    /// A live `notify` reaches an already-subscribed stream.
+    #[test]
+    fn notify_reaches_subscribed_stream() {
+        let factory = AdminServiceEventSubscriberFactory::new();
+        let mut stream = factory.subscribe(None, no_replay).expect("failed to subscribe");
+
+        let event = sample_event(1);
+        factory.notify(&event);
+
+        match stream.next() {
+            Some(SubscriptionEvent::Event(delivered)) => assert_eq!(delivered.event_id, 1),
+            other => panic!("expected a live event, got {:?}", other),
+        }
+    }
+
+    /// Replay and the live stream never double-deliver an event that lands in the gap between
+    /// subscriber registration and the replay query running.
+    #[test]
+    fn live_stream_skips_events_already_covered_by_replay() {
+        let factory = AdminServiceEventSubscriberFactory::new();
+        let replayed = vec![sample_event(1), sample_event(2)];
+        let mut stream = factory
+            .subscribe(None, move |_| Ok(Box::new(replayed.into_iter())))
+            .expect("failed to subscribe");
+
+        // Simulates an event committed while replay was still running: it lands on the live
+        // channel even though it was also captured by the replay above.
+        factory.notify(&sample_event(2));
+        factory.notify(&sample_event(3));
+
+        assert_eq!(next_event_id(&mut stream), 1);
+        assert_eq!(next_event_id(&mut stream), 2);
+        // The duplicate delivery of event 2 on the live channel is skipped; event 3 is next.
+        assert_eq!(next_event_id(&mut stream), 3);
+    }
+
+    /// A subscriber whose channel fills is dropped and signaled with a resume cursor instead of
+    /// buffering unboundedly.
+    #[test]
+    fn lagged_subscriber_is_dropped_with_resume_cursor() {
+        let factory = AdminServiceEventSubscriberFactory::new();
+        let mut stream = factory.subscribe(None, no_replay).expect("failed to subscribe");
+
+        for event_id in 1..=(SUBSCRIBER_CHANNEL_CAPACITY as i64 + 1) {
+            factory.notify(&sample_event(event_id));
+        }
+
+        let mut last_event_id = 0;
+        loop {
+            match stream.next() {
+                Some(SubscriptionEvent::Event(event)) => last_event_id = event.event_id,
+                Some(SubscriptionEvent::Lagged(lagged)) => {
+                    assert_eq!(lagged.resume_from, last_event_id);
+                    return;
+                }
+                None => panic!("stream ended without a Lagged signal"),
+            }
+        }
+    }
+
+    fn next_event_id(stream: &mut EventStream) -> i64 {
+        match stream.next() {
+            Some(SubscriptionEvent::Event(event)) => event.event_id,
+            other => panic!("expected a live event, got {:?}", other),
+        }
+    }
+}